@@ -1,6 +1,18 @@
-use mathru::statistics::distrib::{Normal, Distribution};
+mod summary;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Normal, Uniform};
 use rayon::prelude::*;
 
+// Samples are drawn and cumsum'd in fixed-size batches rather than one at a time, so the
+// data-dependent "have we crossed the criterion yet" branch only has to run once per batch
+// instead of once per sample - everything else in the batch (the cumsum, the compare
+// against both boundaries) is a tight, branch-free loop that LLVM auto-vectorises at
+// release opt levels. We rely on auto-vectorisation rather than `std::simd` so this builds
+// on stable.
+const BATCH: usize = 8;
+
 // Rather than using an array for each parameter/result IE
 //
 // latencies: Vec<usize> 
@@ -12,10 +24,64 @@ use rayon::prelude::*;
 // array, rather than multiple), and generally just makes your cpu caches
 // happier.
 #[derive(Debug)]
-struct ModelResult {
-    latency: usize,
-    response: bool,
-    evidence: Vec<f64>,
+pub(crate) struct ModelResult {
+    pub(crate) latency: usize,
+    pub(crate) response: bool,
+    pub(crate) evidence: Vec<f64>,
+}
+
+// Rough guess at how many samples a rep will take to cross a boundary, used only to size the
+// evidence Vec's initial capacity. The nearer boundary is `distance` away; crossing it takes
+// roughly `distance / |drift|` samples when drift dominates the walk, or roughly
+// `(distance / sdrw)^2` samples when diffusion dominates (drift near zero). We take whichever
+// estimate is smaller, plus one batch of slack, and let the Vec grow past it on the rare slow
+// rep rather than over-reserving for the common fast-crossing case.
+fn expected_latency_estimate(drift: f64, sdrw: f64, criterion: f64, z: f64) -> usize {
+    let distance = z.min(criterion - z).max(1.0);
+    let drift_estimate = distance / drift.abs().max(1e-6);
+    let diffusion_estimate = (distance / sdrw.max(1e-6)).powi(2);
+
+    drift_estimate.min(diffusion_estimate).ceil() as usize + BATCH
+}
+
+// Bundles the full Ratcliff diffusion model's bias and between-trial-variability parameters,
+// which otherwise would have been five consecutive, easily-transposed `f64` arguments to
+// `execute_model`.
+#[derive(Debug, Clone, Copy)]
+struct DiffusionParams {
+    // %z is the starting point of the accumulator, modelling response bias - the walk begins
+    // at z rather than at the midpoint, with boundaries at 0 (response = false) and criterion
+    // (response = true) instead of at +/-criterion.
+    z: f64,
+    // %ter is the non-decision time (encoding + motor response) - a fixed delay added on top
+    // of the decision latency, since the accumulator only models the decision itself.
+    t_er: f64,
+    // Between-trial variability in drift rate: each rep resamples its own drift once, up
+    // front, from Normal(drift, eta), rather than using the same mean drift for every rep.
+    eta: f64,
+    // Between-trial variability in starting point: each rep resamples z once, up front, from
+    // Uniform(z - sz/2, z + sz/2).
+    sz: f64,
+    // Between-trial variability in non-decision time: each rep resamples t_er once, up front,
+    // from Uniform(t_er - st/2, t_er + st/2).
+    st: f64,
+}
+
+// Execution-control knobs that aren't part of the diffusion model itself, bundled for the
+// same reason as `DiffusionParams` - `execute_model` was still one argument over clippy's
+// too_many_arguments threshold with these left as loose parameters.
+#[derive(Debug, Clone, Copy)]
+struct ExecutionOptions {
+    // When false, the per-rep evidence trace is never pushed to at all, so a rep allocates
+    // nothing on the heap - only `latency` and `response` are returned. Flip this off for
+    // large `num_reps` / `max_samples` runs where retaining every full trace would otherwise
+    // blow out memory, and you only need the scalar outcomes anyway.
+    record_evidence: bool,
+    // Seed for the per-rep RNGs. Given the same seed, reps, and parameters, the resulting
+    // Vec<ModelResult> is bit-identical regardless of thread count or how rayon schedules
+    // work - each rep's RNG is derived purely from (seed, rep_index), never from a shared
+    // global source, so there's no cross-talk between threads to make results order-dependent.
+    seed: u64,
 }
 
 fn execute_model(
@@ -23,15 +89,20 @@ fn execute_model(
     num_reps: usize,
     // Upper bound on how many samples before we declare this attempt a failure, and skip.
     max_samples: usize,
-    // % amount of evidence that is avaliable during sampling (the higher the drift rate the larger the "steps"). 
+    // % amount of evidence that is avaliable during sampling (the higher the drift rate the larger the "steps").
     drift: f64,
-    // %sdrw is the amount of noise that exists, using a standard deviation distribution from which we will sample the evidence. 
+    // %sdrw is the amount of noise that exists, using a standard deviation distribution from which we will sample the evidence.
     sdrw: f64,
-    // %the decision threshhold is reached once the drift rate reaches 3. aka The distance between the two boundaries 
+    // %the decision threshhold is reached once the drift rate reaches 3. aka The distance between the two boundaries
     criterion: f64,
+    // The full Ratcliff model's bias and between-trial-variability parameters, bundled so
+    // they can't be transposed against each other at the call site.
+    diffusion: DiffusionParams,
+    // Execution-control knobs, bundled for the same reason as `diffusion`.
+    options: ExecutionOptions,
 ) -> Result<Vec<ModelResult>, ()> {
-
-    let distrib: Normal<f64> = Normal::new(drift, sdrw);
+    let DiffusionParams { z, t_er, eta, sz, st } = diffusion;
+    let ExecutionOptions { record_evidence, seed } = options;
 
     // This creates a thread pool, and runs each "attempt" on a different CPU core. Because there
     // is no cross-talk/relationship between samples, these are all calculated indepedently, this
@@ -42,28 +113,98 @@ fn execute_model(
     // of reps here, which means that we avoid costly reallocs.
     let results: Vec<ModelResult> = (0..num_reps)
         .into_par_iter()
-        .filter_map(|_i| {
+        .filter_map(|i| {
 
-        let mut acc: f64 = 0.0;
-        let mut evidence: Vec<f64> = Vec::with_capacity(max_samples);
+        // Each rep gets its own RNG, seeded deterministically from (seed, rep_index) and
+        // constructed here inside the closure so no RNG state is ever shared across threads.
+        // This is what makes the output reproducible - it no longer matters which core picked
+        // up which rep, or in what order.
+        let rep_seed = seed ^ (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let mut rng = ChaCha8Rng::seed_from_u64(rep_seed);
+
+        // Between-trial variability: drift, starting point, and non-decision time are each
+        // resampled once per rep, before the walk begins, rather than held fixed across reps -
+        // this is what lets the model reproduce the differing shapes of correct vs. error RT
+        // distributions. A variability parameter of 0 collapses back to the fixed value.
+        let rep_drift = if eta > 0.0 {
+            Normal::new(drift, eta)
+                .expect("Invalid drift/eta for Normal distribution")
+                .sample(&mut rng)
+        } else {
+            drift
+        };
+        let rep_z = if sz > 0.0 {
+            Uniform::new(z - sz / 2.0, z + sz / 2.0).sample(&mut rng)
+        } else {
+            z
+        };
+        let rep_t_er = if st > 0.0 {
+            // Clamped at 0 - a non-decision time can't be negative, but a large enough `st`
+            // relative to `t_er` would otherwise let the Uniform sample below it, which then
+            // silently saturates to a latency offset of 0 once rounded and cast to `usize`.
+            Uniform::new(0.0f64.max(t_er - st / 2.0), t_er + st / 2.0).sample(&mut rng)
+        } else {
+            t_er
+        };
+
+        let distrib =
+            Normal::new(rep_drift, sdrw).expect("Invalid drift/sdrw for Normal distribution");
+
+        let mut acc: f64 = rep_z;
+        // When recording, size the capacity to a realistic expected-latency estimate rather
+        // than max_samples - the common case is a fast crossing, so reserving the full
+        // max_samples up front over-allocates for almost every rep.
+        let mut evidence: Vec<f64> = if record_evidence {
+            let capacity =
+                expected_latency_estimate(rep_drift, sdrw, criterion, rep_z).min(max_samples);
+            Vec::with_capacity(capacity)
+        } else {
+            Vec::new()
+        };
 
         // Rather than generate all the samples, then walk through them to find the point at which
-        // we have reached the decision threshold, we generate each sample one at a time and
-        // continue to process and accumlate that, shortcutting (early-return) when we have
-        // passed the criterion (decision point).
+        // we have reached the decision threshold, we generate and cumsum a batch of BATCH
+        // samples at a time, and only fall back to a scalar scan (to pin down the exact
+        // within-batch crossing index) once per batch rather than once per sample.
         //
-        for latency in 0..max_samples {
-            // %generated a distribution of randomly sampled evidence with a mean of drift and standard deviation of sdrw
-            let v = distrib.random();
+        let mut base = 0;
+        while base < max_samples {
+            // The last batch may run past max_samples, so only `batch_len` of the BATCH
+            // lanes are real samples - the rest are zero-filled padding that just repeats
+            // the final real cumulative value and can never register a spurious crossing.
+            let batch_len = BATCH.min(max_samples - base);
+
+            let mut raw = [0.0f64; BATCH];
+            for slot in raw.iter_mut().take(batch_len) {
+                // %generated a distribution of randomly sampled evidence with a mean of drift and standard deviation of sdrw
+                *slot = distrib.sample(&mut rng);
+            }
+
             // %the accumulation of that evidence is calculated
             // evidence(i,:) = cumsum([0 genSample]);
-            acc = acc + v;
-            evidence.push(acc);
+            let mut cum = [0.0f64; BATCH];
+            let mut running = acc;
+            for (c, v) in cum.iter_mut().zip(raw.iter()) {
+                running += v;
+                *c = running;
+            }
+
+            // %calculate p, the first value to reach either decision boundary - the upper
+            // boundary at criterion (response = true) or the lower boundary at 0 (response = false).
+            //     p = find((evidence(i,:) > criterion) | (evidence(i,:) < 0),1);
+            let mut crossed = [false; BATCH];
+            for (flag, &v) in crossed.iter_mut().zip(cum.iter()) {
+                *flag = v >= criterion || v <= 0.0;
+            }
 
-            // %calculate p, the first value to reach the decision threshold. 
-            //     p = find((abs(evidence(i,:)) > criterion),1); 
-            if acc.abs() > criterion {
-                let response = acc.is_sign_positive();
+            if let Some(hit) = (0..batch_len).find(|&i| crossed[i]) {
+                if record_evidence {
+                    evidence.extend_from_slice(&cum[..=hit]);
+                }
+                acc = cum[hit];
+                let response = acc >= criterion;
+                // %ter, the non-decision time, is added on top of the decision latency itself.
+                let latency = base + hit + rep_t_er.round() as usize;
                 // Complete, build the result. Wrapping in the "Some" variant for Option
                 // indicates to the iterator that we succedded and that we should keep this
                 // valid result.
@@ -73,6 +214,12 @@ fn execute_model(
                     evidence,
                 });
             }
+
+            if record_evidence {
+                evidence.extend_from_slice(&cum[..batch_len]);
+            }
+            acc = cum[batch_len - 1];
+            base += batch_len;
         }
 
         // If we were unable to get enough samples, log an error message to the display,
@@ -91,6 +238,170 @@ fn execute_model(
     Ok(results)
 }
 
+// Solve the square system `a * x = b` via Gaussian elimination with partial pivoting.
+// `a` and `b` are consumed since the elimination happens in place.
+fn solve_linear_system(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Vec<f64> {
+    let n = b.len();
+
+    for col in 0..n {
+        let mut pivot = col;
+        for row in (col + 1)..n {
+            if a[row][col].abs() > a[pivot][col].abs() {
+                pivot = row;
+            }
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let diag = a[col][col];
+        for row in (col + 1)..n {
+            // `row > col` always holds here, so split the matrix at `row` to borrow the
+            // pivot row (`a[col]`, in the first half) and the row being eliminated
+            // (`a[row]`, the first row of the second half) at the same time.
+            let (pivot_half, row_half) = a.split_at_mut(row);
+            let pivot_row = &pivot_half[col];
+            let cur_row = &mut row_half[0];
+
+            let factor = cur_row[col] / diag;
+            cur_row[col..n]
+                .iter_mut()
+                .zip(&pivot_row[col..n])
+                .for_each(|(x, y)| *x -= factor * y);
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum = b[row]
+            - a[row][(row + 1)..n]
+                .iter()
+                .zip(&x[(row + 1)..n])
+                .map(|(a_ik, x_k)| a_ik * x_k)
+                .sum::<f64>();
+        x[row] = sum / a[row][row];
+    }
+    x
+}
+
+// Builds the fixed Savitzky-Golay coefficient vector `b` for a half-window of `half_window`
+// samples either side of the centre point, fitted with a polynomial of `order`.
+//
+// This is a precompute-once-apply-many convolution: we build the (2m+1)x(p+1) Vandermonde
+// matrix J where J[i][j] = i^j for i in [-m, m], then solve (J^T J) c = e_0 for the first
+// column of (J^T J)^-1 (rather than inverting the whole matrix, since we only need row 0 of
+// A = (J^T J)^-1 J^T). The returned vector is `c^T J^T`, i.e. row 0 of A.
+fn savitzky_golay_coefficients(half_window: usize, order: usize) -> Vec<f64> {
+    assert!(
+        order < 2 * half_window + 1,
+        "polynomial order must be < 2 * half_window + 1"
+    );
+
+    let m = half_window as isize;
+    let window = 2 * half_window + 1;
+    let p = order + 1;
+
+    // jt[j][k] = J^T[j][k] = J[k][j] = i^j, where i runs over [-m, m] indexed by k
+    let jt: Vec<Vec<f64>> = (0..p)
+        .map(|j| {
+            (-m..=m)
+                .map(|i| (i as f64).powi(j as i32))
+                .collect::<Vec<f64>>()
+        })
+        .collect();
+
+    let jt_j: Vec<Vec<f64>> = (0..p)
+        .map(|a| {
+            (0..p)
+                .map(|b| (0..window).map(|k| jt[a][k] * jt[b][k]).sum())
+                .collect::<Vec<f64>>()
+        })
+        .collect();
+
+    let mut e0 = vec![0.0; p];
+    e0[0] = 1.0;
+    let c = solve_linear_system(jt_j, e0);
+
+    (0..window)
+        .map(|k| (0..p).map(|j| c[j] * jt[j][k]).sum())
+        .collect()
+}
+
+// All the Savitzky-Golay coefficient vectors a trace of any length could need for a given
+// (half_window, order): the fixed main-window coefficients, plus one set per possible shrunk
+// edge window (0..=half_window). Building this once and sharing it across every trace is the
+// precompute-once-apply-many the coefficients were always meant to be - solving the
+// Vandermonde system is the expensive part, and a batch of traces all shares the same
+// half_window/order.
+struct SavitzkyGolayCoefficients {
+    half_window: usize,
+    main: Vec<f64>,
+    edge: Vec<Vec<f64>>,
+}
+
+impl SavitzkyGolayCoefficients {
+    fn new(half_window: usize, order: usize) -> Self {
+        let main = savitzky_golay_coefficients(half_window, order);
+        let edge = (0..=half_window)
+            .map(|w| savitzky_golay_coefficients(w, order.min(2 * w)))
+            .collect();
+        SavitzkyGolayCoefficients {
+            half_window,
+            main,
+            edge,
+        }
+    }
+}
+
+// Smooths a single evidence trace with a Savitzky-Golay filter: y_smooth[k] is the sliding
+// dot product of the fixed coefficient vector `b` against the `2*half_window + 1` samples
+// centred on `k`. Near the ends of the trace, where the full window would run off the edge,
+// the window is shrunk to fit and the matching precomputed edge coefficients are used
+// instead.
+fn savitzky_golay_smooth(trace: &[f64], coeffs: &SavitzkyGolayCoefficients) -> Vec<f64> {
+    if trace.is_empty() {
+        return Vec::new();
+    }
+
+    let half_window = coeffs.half_window;
+
+    (0..trace.len())
+        .map(|k| {
+            if k >= half_window && k + half_window < trace.len() {
+                coeffs
+                    .main
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| c * trace[k + i - half_window])
+                    .sum()
+            } else {
+                let w = half_window.min(k).min(trace.len() - 1 - k);
+                coeffs.edge[w]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| c * trace[k + i - w])
+                    .sum()
+            }
+        })
+        .collect()
+}
+
+// Applies the Savitzky-Golay filter to every rep's evidence trace, returning a smoothed copy
+// of each one. Useful for visualising the drift process without the per-sample Gaussian
+// jitter getting in the way. The coefficients only depend on (half_window, order), not on
+// the trace itself, so they're solved for once here rather than once per trace. Traces are
+// independent of one another, so just like `execute_model` we smooth them all in parallel.
+fn smooth_evidence_traces(
+    results: &[ModelResult],
+    half_window: usize,
+    order: usize,
+) -> Vec<Vec<f64>> {
+    let coeffs = SavitzkyGolayCoefficients::new(half_window, order);
+    results
+        .par_iter()
+        .map(|r| savitzky_golay_smooth(&r.evidence, &coeffs))
+        .collect()
+}
 
 fn main() {
     println!("Hello, world!");
@@ -100,6 +411,25 @@ fn main() {
 mod tests {
     use super::*;
 
+    // Most tests only care about bias (z) and non-decision time (t_er), with no
+    // between-trial variability, so this covers that common case.
+    fn diffusion(z: f64, t_er: f64) -> DiffusionParams {
+        DiffusionParams {
+            z,
+            t_er,
+            eta: 0.0,
+            sz: 0.0,
+            st: 0.0,
+        }
+    }
+
+    fn options(record_evidence: bool, seed: u64) -> ExecutionOptions {
+        ExecutionOptions {
+            record_evidence,
+            seed,
+        }
+    }
+
     #[test]
     fn do_the_thang() {
         // let reps = 10;
@@ -113,8 +443,176 @@ mod tests {
             0.1,
             0.3,
             3.0,
+            diffusion(1.5, 0.0),
+            options(true, 42),
         )
         .expect("Failed to run model");
         eprintln!("successful samples -> {:?}", res.len());
     }
+
+    #[test]
+    fn same_seed_same_results() {
+        let reps = 256;
+        let bound = 1000;
+
+        let a = execute_model(
+            reps,
+            bound,
+            0.1,
+            0.3,
+            3.0,
+            diffusion(1.5, 0.0),
+            options(true, 1234),
+        )
+        .expect("Failed to run model");
+        let b = execute_model(
+            reps,
+            bound,
+            0.1,
+            0.3,
+            3.0,
+            diffusion(1.5, 0.0),
+            options(true, 1234),
+        )
+        .expect("Failed to run model");
+
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert_eq!(x.latency, y.latency);
+            assert_eq!(x.response, y.response);
+            assert_eq!(x.evidence, y.evidence);
+        }
+    }
+
+    #[test]
+    fn non_decision_time_is_a_fixed_offset_on_latency() {
+        // With no between-trial variability, t_er never touches the RNG, so two runs that
+        // only differ in t_er should walk identically and just end up offset by the
+        // difference in (rounded) t_er.
+        let reps = 64;
+        let bound = 1000;
+
+        let fast = execute_model(
+            reps,
+            bound,
+            0.1,
+            0.3,
+            3.0,
+            diffusion(1.5, 0.0),
+            options(true, 7),
+        )
+        .expect("Failed to run model");
+        let slow = execute_model(
+            reps,
+            bound,
+            0.1,
+            0.3,
+            3.0,
+            diffusion(1.5, 50.0),
+            options(true, 7),
+        )
+        .expect("Failed to run model");
+
+        assert_eq!(fast.len(), slow.len());
+        for (f, s) in fast.iter().zip(slow.iter()) {
+            assert_eq!(f.latency + 50, s.latency);
+            assert_eq!(f.response, s.response);
+            assert_eq!(f.evidence, s.evidence);
+        }
+    }
+
+    #[test]
+    fn starting_point_bias_favours_the_nearer_boundary() {
+        // With the starting point z pushed right up against the upper boundary, almost every
+        // rep should cross upward (response = true) almost immediately.
+        let reps = 200;
+        let bound = 1000;
+
+        let res = execute_model(
+            reps,
+            bound,
+            0.0,
+            0.3,
+            3.0,
+            diffusion(2.9, 0.0),
+            options(true, 55),
+        )
+        .expect("Failed to run model");
+
+        let true_responses = res.iter().filter(|r| r.response).count();
+        assert!(true_responses as f64 / res.len() as f64 > 0.9);
+    }
+
+    #[test]
+    fn record_evidence_false_keeps_traces_empty() {
+        let reps = 64;
+        let bound = 1000;
+
+        let with_evidence = execute_model(
+            reps,
+            bound,
+            0.1,
+            0.3,
+            3.0,
+            diffusion(1.5, 0.0),
+            options(true, 21),
+        )
+        .expect("Failed to run model");
+        let lean = execute_model(
+            reps,
+            bound,
+            0.1,
+            0.3,
+            3.0,
+            diffusion(1.5, 0.0),
+            options(false, 21),
+        )
+        .expect("Failed to run model");
+
+        assert_eq!(with_evidence.len(), lean.len());
+        for (full, thin) in with_evidence.iter().zip(lean.iter()) {
+            assert_eq!(full.latency, thin.latency);
+            assert_eq!(full.response, thin.response);
+            assert!(thin.evidence.is_empty());
+            assert!(!full.evidence.is_empty());
+        }
+    }
+
+    #[test]
+    fn savitzky_golay_reproduces_linear_trace_exactly() {
+        // A Savitzky-Golay filter of order >= 1 should leave a perfectly linear trace
+        // untouched, including at the shrunk-window edges.
+        let trace: Vec<f64> = (0..50).map(|i| 2.0 * i as f64 + 1.0).collect();
+
+        let coeffs = SavitzkyGolayCoefficients::new(5, 2);
+        let smoothed = savitzky_golay_smooth(&trace, &coeffs);
+
+        for (orig, smooth) in trace.iter().zip(smoothed.iter()) {
+            assert!((orig - smooth).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn smooth_evidence_traces_matches_per_trace_smoothing() {
+        let reps = 32;
+        let bound = 1000;
+
+        let res = execute_model(
+            reps,
+            bound,
+            0.1,
+            0.3,
+            3.0,
+            diffusion(1.5, 0.0),
+            options(true, 99),
+        )
+        .expect("Failed to run model");
+        let smoothed = smooth_evidence_traces(&res, 5, 2);
+
+        let coeffs = SavitzkyGolayCoefficients::new(5, 2);
+        assert_eq!(smoothed.len(), res.len());
+        for (r, s) in res.iter().zip(smoothed.iter()) {
+            assert_eq!(s, &savitzky_golay_smooth(&r.evidence, &coeffs));
+        }
+    }
 }
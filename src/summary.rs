@@ -0,0 +1,247 @@
+use rayon::prelude::*;
+
+use crate::ModelResult;
+
+// The RT quantiles psychologists actually report when comparing observed vs. simulated
+// distributions.
+const QUANTILES: [f64; 5] = [0.1, 0.3, 0.5, 0.7, 0.9];
+
+// One bin of the conditional accuracy function: accuracy for trials whose latency falls in
+// [bin_start, bin_end).
+#[derive(Debug, Clone, Copy)]
+pub struct ConditionalAccuracyBin {
+    pub bin_start: usize,
+    pub bin_end: usize,
+    pub accuracy: f64,
+    pub count: usize,
+}
+
+// Summary statistics for a batch of ModelResults, the kind of thing that actually gets
+// reported in a paper rather than the raw per-rep traces.
+#[derive(Debug, Clone)]
+pub struct ModelSummary {
+    pub accuracy: f64,
+    pub mean_latency: f64,
+    pub latency_variance: f64,
+    pub correct_quantiles: [f64; 5],
+    pub error_quantiles: [f64; 5],
+    pub conditional_accuracy: Vec<ConditionalAccuracyBin>,
+}
+
+// Running totals for accuracy/mean/variance, built up one result at a time and merged
+// pairwise - this is the "fold" half of a parallel reduce over `results`, so no per-field
+// Vec (all the latencies, all the responses) ever gets materialised just to take a sum.
+#[derive(Clone, Copy)]
+struct Totals {
+    count: usize,
+    correct_count: usize,
+    sum_latency: f64,
+    sum_sq_latency: f64,
+}
+
+impl Totals {
+    fn zero() -> Self {
+        Totals {
+            count: 0,
+            correct_count: 0,
+            sum_latency: 0.0,
+            sum_sq_latency: 0.0,
+        }
+    }
+
+    fn push(mut self, result: &ModelResult) -> Self {
+        self.count += 1;
+        if result.response {
+            self.correct_count += 1;
+        }
+        let latency = result.latency as f64;
+        self.sum_latency += latency;
+        self.sum_sq_latency += latency * latency;
+        self
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Totals {
+            count: self.count + other.count,
+            correct_count: self.correct_count + other.correct_count,
+            sum_latency: self.sum_latency + other.sum_latency,
+            sum_sq_latency: self.sum_sq_latency + other.sum_sq_latency,
+        }
+    }
+}
+
+// Linear-interpolation-free quantiles: pick the nearest-rank sample in the (already sorted)
+// latencies. Good enough for the bin counts we deal with here, and keeps this a single pass
+// over `qs` rather than reaching for a stats crate.
+fn quantiles_of(sorted_latencies: &[usize], qs: &[f64; 5]) -> [f64; 5] {
+    if sorted_latencies.is_empty() {
+        return [f64::NAN; 5];
+    }
+
+    let mut out = [0.0; 5];
+    for (slot, &q) in out.iter_mut().zip(qs.iter()) {
+        let idx = (((sorted_latencies.len() - 1) as f64) * q).round() as usize;
+        *slot = sorted_latencies[idx] as f64;
+    }
+    out
+}
+
+// Bins trials by latency (bucket width `bin_width`) and reports the accuracy within each
+// non-empty bucket - the conditional accuracy function. Like the totals in `summarise`, the
+// per-bin counts are built with a parallel fold/reduce over `results` rather than by
+// collecting per-bin Vec<ModelResult>. The RT quantiles in `summarise` are the exception:
+// they collect the correct and error latencies into their own Vecs and sort them.
+fn conditional_accuracy_function(
+    results: &[ModelResult],
+    bin_width: usize,
+) -> Vec<ConditionalAccuracyBin> {
+    let max_latency = results.iter().map(|r| r.latency).max().unwrap_or(0);
+    let num_bins = max_latency / bin_width + 1;
+
+    let bins: Vec<(usize, usize)> = results
+        .par_iter()
+        .fold(
+            || vec![(0usize, 0usize); num_bins],
+            |mut counts, result| {
+                let bin = result.latency / bin_width;
+                let (count, correct_count) = &mut counts[bin];
+                *count += 1;
+                if result.response {
+                    *correct_count += 1;
+                }
+                counts
+            },
+        )
+        .reduce(
+            || vec![(0usize, 0usize); num_bins],
+            |mut a, b| {
+                for (x, y) in a.iter_mut().zip(b.iter()) {
+                    x.0 += y.0;
+                    x.1 += y.1;
+                }
+                a
+            },
+        );
+
+    bins.into_iter()
+        .enumerate()
+        .filter(|(_, (count, _))| *count > 0)
+        .map(|(bin, (count, correct_count))| ConditionalAccuracyBin {
+            bin_start: bin * bin_width,
+            bin_end: (bin + 1) * bin_width,
+            accuracy: correct_count as f64 / count as f64,
+            count,
+        })
+        .collect()
+}
+
+// Builds the full summary for a batch of results: overall accuracy, mean/variance of
+// latency, correct/error RT quantiles, and the conditional accuracy function binned by
+// `bin_width` samples.
+pub fn summarise(results: &[ModelResult], bin_width: usize) -> ModelSummary {
+    assert!(bin_width > 0, "bin_width must be greater than zero");
+
+    let totals = results
+        .par_iter()
+        .fold(Totals::zero, Totals::push)
+        .reduce(Totals::zero, Totals::merge);
+
+    let mean_latency = totals.sum_latency / totals.count as f64;
+    let latency_variance =
+        totals.sum_sq_latency / totals.count as f64 - mean_latency * mean_latency;
+    let accuracy = totals.correct_count as f64 / totals.count as f64;
+
+    let mut correct_latencies: Vec<usize> = results
+        .par_iter()
+        .filter(|r| r.response)
+        .map(|r| r.latency)
+        .collect();
+    let mut error_latencies: Vec<usize> = results
+        .par_iter()
+        .filter(|r| !r.response)
+        .map(|r| r.latency)
+        .collect();
+    correct_latencies.par_sort_unstable();
+    error_latencies.par_sort_unstable();
+
+    ModelSummary {
+        accuracy,
+        mean_latency,
+        latency_variance,
+        correct_quantiles: quantiles_of(&correct_latencies, &QUANTILES),
+        error_quantiles: quantiles_of(&error_latencies, &QUANTILES),
+        conditional_accuracy: conditional_accuracy_function(results, bin_width),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(latency: usize, response: bool) -> ModelResult {
+        ModelResult {
+            latency,
+            response,
+            evidence: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn summarises_accuracy_and_latency_moments() {
+        let results = vec![
+            result(10, true),
+            result(20, true),
+            result(30, false),
+            result(40, false),
+        ];
+
+        let summary = summarise(&results, 10);
+
+        assert_eq!(summary.accuracy, 0.5);
+        assert_eq!(summary.mean_latency, 25.0);
+        assert_eq!(summary.latency_variance, 125.0);
+    }
+
+    #[test]
+    fn separates_quantiles_by_response() {
+        let results = vec![
+            result(10, true),
+            result(20, true),
+            result(30, true),
+            result(100, false),
+        ];
+
+        let summary = summarise(&results, 10);
+
+        assert_eq!(summary.correct_quantiles[2], 20.0);
+        assert_eq!(summary.error_quantiles[0], 100.0);
+    }
+
+    #[test]
+    fn conditional_accuracy_is_binned_by_latency() {
+        let results = vec![
+            result(1, true),
+            result(2, false),
+            result(15, true),
+            result(18, true),
+        ];
+
+        let summary = summarise(&results, 10);
+
+        let first_bin = summary
+            .conditional_accuracy
+            .iter()
+            .find(|b| b.bin_start == 0)
+            .expect("expected a [0, 10) bin");
+        assert_eq!(first_bin.count, 2);
+        assert_eq!(first_bin.accuracy, 0.5);
+
+        let second_bin = summary
+            .conditional_accuracy
+            .iter()
+            .find(|b| b.bin_start == 10)
+            .expect("expected a [10, 20) bin");
+        assert_eq!(second_bin.count, 2);
+        assert_eq!(second_bin.accuracy, 1.0);
+    }
+}